@@ -0,0 +1,211 @@
+//! A minimal writer for Motion-JPEG AVI files: MJPEG streams are already a sequence of
+//! independent JPEG frames, so recording is just wrapping each one in an `00dc` chunk inside a
+//! `movi` list, bookended by the usual RIFF/`hdrl` headers and an `idx1` index.
+
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+const AVIF_HASINDEX: u32 = 0x10;
+const AVIIF_KEYFRAME: u32 = 0x10; // every MJPEG frame stands alone, so all are keyframes
+
+struct FrameIndex {
+    offset: u32,
+    size: u32,
+}
+
+/// Incrementally writes a Motion-JPEG AVI to disk. Call [`write_frame`](Self::write_frame) for
+/// each captured JPEG, then [`finish`](Self::finish) to patch the header sizes and append the
+/// `idx1` index.
+pub struct AviWriter {
+    file: File,
+    frame_count: u32,
+    riff_size_pos: u64,
+    total_frames_pos: u64,
+    stream_length_pos: u64,
+    movi_size_pos: u64,
+    movi_data_start: u64,
+    index: Vec<FrameIndex>,
+}
+
+impl AviWriter {
+    /// Creates `path` and writes placeholder RIFF/`hdrl` headers sized for `width`x`height`
+    /// frames at `fps_scale`/`fps_rate` (i.e. `fps_rate / fps_scale` frames per second, matching
+    /// V4L2's `Fraction`), to be patched in [`finish`](Self::finish).
+    pub fn create(path: &Path, width: u32, height: u32, fps_scale: u32, fps_rate: u32) -> Result<Self> {
+        let mut file = File::create(path).context("failed to create avi file")?;
+        let micro_sec_per_frame = 1_000_000u64 * fps_scale as u64 / fps_rate.max(1) as u64;
+
+        file.write_all(b"RIFF")?;
+        let riff_size_pos = file.stream_position()?;
+        write_u32(&mut file, 0)?; // total size, patched in `finish`
+        file.write_all(b"AVI ")?;
+
+        file.write_all(b"LIST")?;
+        let hdrl_size_pos = file.stream_position()?;
+        write_u32(&mut file, 0)?;
+        file.write_all(b"hdrl")?;
+
+        file.write_all(b"avih")?;
+        write_u32(&mut file, 56)?;
+        write_u32(&mut file, micro_sec_per_frame as u32)?;
+        write_u32(&mut file, 0)?; // dwMaxBytesPerSec, unknown up front
+        write_u32(&mut file, 0)?; // dwPaddingGranularity
+        write_u32(&mut file, AVIF_HASINDEX)?;
+        let total_frames_pos = file.stream_position()?;
+        write_u32(&mut file, 0)?; // dwTotalFrames, patched in `finish`
+        write_u32(&mut file, 0)?; // dwInitialFrames
+        write_u32(&mut file, 1)?; // dwStreams
+        write_u32(&mut file, 0)?; // dwSuggestedBufferSize
+        write_u32(&mut file, width)?;
+        write_u32(&mut file, height)?;
+        write_u32(&mut file, 0)?; // dwReserved[0..4]
+        write_u32(&mut file, 0)?;
+        write_u32(&mut file, 0)?;
+        write_u32(&mut file, 0)?;
+
+        file.write_all(b"LIST")?;
+        let strl_size_pos = file.stream_position()?;
+        write_u32(&mut file, 0)?;
+        file.write_all(b"strl")?;
+
+        file.write_all(b"strh")?;
+        write_u32(&mut file, 64)?;
+        file.write_all(b"vids")?;
+        file.write_all(b"MJPG")?;
+        write_u32(&mut file, 0)?; // dwFlags
+        write_u16(&mut file, 0)?; // wPriority
+        write_u16(&mut file, 0)?; // wLanguage
+        write_u32(&mut file, 0)?; // dwInitialFrames
+        write_u32(&mut file, fps_scale)?;
+        write_u32(&mut file, fps_rate)?;
+        write_u32(&mut file, 0)?; // dwStart
+        let stream_length_pos = file.stream_position()?;
+        write_u32(&mut file, 0)?; // dwLength, patched in `finish`
+        write_u32(&mut file, 0)?; // dwSuggestedBufferSize
+        write_u32(&mut file, u32::MAX)?; // dwQuality: unspecified
+        write_u32(&mut file, 0)?; // dwSampleSize
+        write_u32(&mut file, 0)?; // rcFrame.left
+        write_u32(&mut file, 0)?; // rcFrame.top
+        write_u32(&mut file, width)?; // rcFrame.right
+        write_u32(&mut file, height)?; // rcFrame.bottom
+
+        file.write_all(b"strf")?;
+        write_u32(&mut file, 40)?;
+        write_u32(&mut file, 40)?; // biSize
+        write_u32(&mut file, width)?;
+        write_u32(&mut file, height)?;
+        write_u16(&mut file, 1)?; // biPlanes
+        write_u16(&mut file, 24)?; // biBitCount
+        file.write_all(b"MJPG")?; // biCompression
+        write_u32(&mut file, width * height * 3)?; // biSizeImage
+        write_u32(&mut file, 0)?; // biXPelsPerMeter
+        write_u32(&mut file, 0)?; // biYPelsPerMeter
+        write_u32(&mut file, 0)?; // biClrUsed
+        write_u32(&mut file, 0)?; // biClrImportant
+
+        patch_list_size(&mut file, strl_size_pos)?;
+        patch_list_size(&mut file, hdrl_size_pos)?;
+
+        file.write_all(b"LIST")?;
+        let movi_size_pos = file.stream_position()?;
+        write_u32(&mut file, 0)?; // movi list size, patched in `finish`
+        file.write_all(b"movi")?;
+
+        let movi_data_start = file.stream_position()?;
+
+        Ok(Self {
+            file,
+            frame_count: 0,
+            riff_size_pos,
+            total_frames_pos,
+            stream_length_pos,
+            movi_size_pos,
+            movi_data_start,
+            index: Vec::new(),
+        })
+    }
+
+    /// Appends one JPEG frame as an `00dc` chunk.
+    pub fn write_frame(&mut self, jpg: &[u8]) -> Result<()> {
+        let offset = (self.file.stream_position()? - self.movi_data_start) as u32;
+
+        self.file.write_all(b"00dc")?;
+        write_u32(&mut self.file, jpg.len() as u32)?;
+        self.file.write_all(jpg)?;
+        if jpg.len() % 2 == 1 {
+            self.file.write_all(&[0])?; // chunks are word-aligned
+        }
+
+        self.index.push(FrameIndex {
+            offset,
+            size: jpg.len() as u32,
+        });
+        self.frame_count += 1;
+
+        Ok(())
+    }
+
+    /// Number of frames written so far.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Appends the `idx1` index and patches the RIFF/`movi`/header sizes now that the frame
+    /// count and total size are known.
+    pub fn finish(mut self) -> Result<()> {
+        let movi_end = self.file.stream_position()?;
+
+        self.file.write_all(b"idx1")?;
+        write_u32(&mut self.file, (self.index.len() * 16) as u32)?;
+        for frame in &self.index {
+            self.file.write_all(b"00dc")?;
+            write_u32(&mut self.file, AVIIF_KEYFRAME)?;
+            write_u32(&mut self.file, frame.offset)?;
+            write_u32(&mut self.file, frame.size)?;
+        }
+
+        let file_end = self.file.stream_position()?;
+
+        patch_list_size_at(&mut self.file, self.movi_size_pos, movi_end)?;
+
+        self.file.seek(SeekFrom::Start(self.total_frames_pos))?;
+        write_u32(&mut self.file, self.frame_count)?;
+
+        self.file.seek(SeekFrom::Start(self.stream_length_pos))?;
+        write_u32(&mut self.file, self.frame_count)?;
+
+        self.file.seek(SeekFrom::Start(self.riff_size_pos))?;
+        write_u32(&mut self.file, (file_end - self.riff_size_pos - 4) as u32)?;
+
+        self.file.flush().context("failed to flush avi file")?;
+        Ok(())
+    }
+}
+
+/// Patches a `LIST`/`RIFF` size field with the distance from just after the field to the file's
+/// current position.
+fn patch_list_size(file: &mut File, size_pos: u64) -> std::io::Result<()> {
+    let end = file.stream_position()?;
+    patch_list_size_at(file, size_pos, end)
+}
+
+fn patch_list_size_at(file: &mut File, size_pos: u64, end: u64) -> std::io::Result<()> {
+    let here = file.stream_position()?;
+    file.seek(SeekFrom::Start(size_pos))?;
+    write_u32(file, (end - size_pos - 4) as u32)?;
+    file.seek(SeekFrom::Start(here))?;
+    Ok(())
+}
+
+fn write_u32<W: Write>(w: &mut W, v: u32) -> std::io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn write_u16<W: Write>(w: &mut W, v: u16) -> std::io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
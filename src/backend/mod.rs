@@ -0,0 +1,243 @@
+//! Backend-neutral capture abstraction, modeled on nokhwa's `CaptureBackendTrait`: everything
+//! platform-specific (V4L2 on Linux, UVC elsewhere) lives behind [`CaptureBackend`] so the rest
+//! of the app only ever deals with [`DeviceInfo`], [`Frame`] and [`ControlDesc`].
+
+use anyhow::Result;
+use eframe::epaint::ColorImage;
+use image::{codecs::jpeg::JpegDecoder, DynamicImage};
+
+#[cfg(feature = "v4l")]
+pub mod v4l;
+
+#[cfg(feature = "uvc")]
+pub mod uvc;
+
+/// A capturable device as reported by a backend, before it's opened.
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    /// Backend-specific handle used to open the device; not necessarily contiguous or stable
+    /// across hotplug events.
+    pub index: usize,
+    pub name: String,
+}
+
+/// The wire format of a captured frame's raw buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    Mjpg,
+    Yuyv,
+}
+
+/// One decoded frame: the raw buffer exactly as the backend produced it (so `Mjpg` frames can be
+/// saved or streamed into a recording without re-encoding), plus the already-decoded image for
+/// display.
+pub struct Frame {
+    pub raw: Vec<u8>,
+    pub format: PixelFormat,
+    pub rgb: ColorImage,
+}
+
+/// Decodes a raw capture buffer into an RGB image, dispatching on `format`.
+pub(crate) fn decode(format: PixelFormat, buf: &[u8], width: u32, height: u32) -> Result<ColorImage> {
+    match format {
+        PixelFormat::Mjpg => decode_mjpg(buf),
+        PixelFormat::Yuyv => Ok(decode_yuyv(buf, width, height)),
+    }
+}
+
+fn decode_mjpg(jpg_img: &[u8]) -> Result<ColorImage> {
+    let de = JpegDecoder::new(jpg_img)?;
+    let img = DynamicImage::from_decoder(de)?.to_rgba8();
+    let size = [img.width() as _, img.height() as _];
+    let egui_img = ColorImage::from_rgba_unmultiplied(size, img.as_flat_samples().as_slice());
+
+    Ok(egui_img)
+}
+
+/// Decodes a packed YUYV 4:2:2 buffer (`[Y0, U, Y1, V]` per 4 bytes, two pixels) into an RGB
+/// image using the BT.601 conversion, for cameras that don't offer MJPG.
+fn decode_yuyv(buf: &[u8], width: u32, height: u32) -> ColorImage {
+    let (width, height) = (width as usize, height as usize);
+    let mut rgba = Vec::with_capacity(width * height * 4);
+
+    for chunk in buf.chunks_exact(4) {
+        let [y0, u, y1, v] = [chunk[0], chunk[1], chunk[2], chunk[3]].map(f32::from);
+
+        for y in [y0, y1] {
+            let r = y + 1.402 * (v - 128.0);
+            let g = y - 0.344 * (u - 128.0) - 0.714 * (v - 128.0);
+            let b = y + 1.772 * (u - 128.0);
+
+            rgba.extend([r, g, b, 255.0].map(|c| c.clamp(0.0, 255.0) as u8));
+        }
+    }
+
+    ColorImage::from_rgba_unmultiplied([width, height], &rgba)
+}
+
+/// The widget a control's value should be edited with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlKind {
+    Integer,
+    Boolean,
+    Menu,
+    /// Reported by the device but not currently editable (e.g. buttons, compound controls).
+    Other,
+}
+
+/// A backend-neutral description of one device control, analogous to nokhwa's
+/// `KnownCameraControl` but keeping the backend's native id for get/set round-trips.
+#[derive(Clone, Debug)]
+pub struct ControlDesc {
+    pub id: u32,
+    pub name: String,
+    pub kind: ControlKind,
+    pub minimum: i64,
+    pub maximum: i64,
+    pub step: i64,
+    pub default: i64,
+    /// Label per value, for `ControlKind::Menu`.
+    pub items: Option<Vec<(i64, String)>>,
+    /// The standard control this is recognized as, if any.
+    pub known: Option<KnownControl>,
+    /// Which sidebar section this control should be grouped under.
+    pub category: ControlCategory,
+}
+
+/// A standard camera control recognized by id, modeled on nokhwa's `KnownCameraControl`: gives a
+/// control a stable identity, a human-friendly label and a category regardless of whatever raw
+/// string (if any) the driver itself reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KnownControl {
+    Brightness,
+    Contrast,
+    Saturation,
+    Hue,
+    Gamma,
+    WhiteBalanceTemperature,
+    Exposure,
+    Focus,
+    Zoom,
+    Pan,
+    Tilt,
+}
+
+impl KnownControl {
+    pub fn label(self) -> &'static str {
+        match self {
+            KnownControl::Brightness => "Brightness",
+            KnownControl::Contrast => "Contrast",
+            KnownControl::Saturation => "Saturation",
+            KnownControl::Hue => "Hue",
+            KnownControl::Gamma => "Gamma",
+            KnownControl::WhiteBalanceTemperature => "White Balance",
+            KnownControl::Exposure => "Exposure",
+            KnownControl::Focus => "Focus",
+            KnownControl::Zoom => "Zoom",
+            KnownControl::Pan => "Pan",
+            KnownControl::Tilt => "Tilt",
+        }
+    }
+
+    pub fn category(self) -> ControlCategory {
+        match self {
+            KnownControl::Brightness
+            | KnownControl::Contrast
+            | KnownControl::Saturation
+            | KnownControl::Hue
+            | KnownControl::Gamma
+            | KnownControl::WhiteBalanceTemperature => ControlCategory::Image,
+            KnownControl::Exposure => ControlCategory::Exposure,
+            KnownControl::Focus | KnownControl::Zoom | KnownControl::Pan | KnownControl::Tilt => {
+                ControlCategory::FocusZoom
+            }
+        }
+    }
+}
+
+/// A sidebar grouping for controls, so the layout is predictable across cameras rather than
+/// dependent on raw driver enum ordering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlCategory {
+    Image,
+    Exposure,
+    FocusZoom,
+    /// Vendor or otherwise-unrecognized controls.
+    Other,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ControlValue {
+    Integer(i64),
+    Boolean(bool),
+}
+
+/// Recognizes a standard V4L2 control id (from `linux/videodev2.h`'s user and camera control
+/// classes), shared by backends whose native ids are V4L2 ids.
+pub(crate) fn known_control(id: u32) -> Option<KnownControl> {
+    const V4L2_CID_BASE: u32 = 0x00980900;
+    const V4L2_CID_CAMERA_CLASS_BASE: u32 = 0x009a0900;
+
+    match id {
+        id if id == V4L2_CID_BASE => Some(KnownControl::Brightness),
+        id if id == V4L2_CID_BASE + 1 => Some(KnownControl::Contrast),
+        id if id == V4L2_CID_BASE + 2 => Some(KnownControl::Saturation),
+        id if id == V4L2_CID_BASE + 3 => Some(KnownControl::Hue),
+        id if id == V4L2_CID_BASE + 16 => Some(KnownControl::Gamma),
+        id if id == V4L2_CID_BASE + 17 => Some(KnownControl::Exposure),
+        id if id == V4L2_CID_BASE + 26 => Some(KnownControl::WhiteBalanceTemperature),
+        id if id == V4L2_CID_CAMERA_CLASS_BASE + 2 => Some(KnownControl::Exposure),
+        id if id == V4L2_CID_CAMERA_CLASS_BASE + 8 => Some(KnownControl::Pan),
+        id if id == V4L2_CID_CAMERA_CLASS_BASE + 9 => Some(KnownControl::Tilt),
+        id if id == V4L2_CID_CAMERA_CLASS_BASE + 10 => Some(KnownControl::Focus),
+        id if id == V4L2_CID_CAMERA_CLASS_BASE + 13 => Some(KnownControl::Zoom),
+        _ => None,
+    }
+}
+
+/// A device appearing or disappearing, as reported by [`CaptureBackend::poll_hotplug`].
+pub enum HotplugEvent {
+    Added(DeviceInfo),
+    Removed(DeviceInfo),
+}
+
+/// Platform-specific video capture, implemented once per backend (`v4l` on Linux, `uvc`
+/// elsewhere). `KCam` only ever talks to a `Box<dyn CaptureBackend>`.
+pub trait CaptureBackend {
+    /// Lists devices capable of being opened by this backend.
+    fn list_devices() -> Result<Vec<DeviceInfo>>
+    where
+        Self: Sized;
+
+    /// Opens the device at `index` and begins streaming.
+    fn open(index: usize) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Fetches and decodes the next available frame.
+    fn next_frame(&mut self) -> Result<Frame>;
+
+    /// Describes the controls available on the open device.
+    fn controls(&self) -> Vec<ControlDesc>;
+    fn control_value(&self, id: u32) -> Result<ControlValue>;
+    fn set_control(&mut self, id: u32, value: ControlValue) -> Result<()>;
+
+    /// Resolutions the open device can be switched to at its current pixel format.
+    fn resolutions(&self) -> Vec<(u32, u32)>;
+    fn active_resolution(&self) -> (u32, u32);
+    /// Tears down and rebuilds the stream at the requested resolution.
+    fn set_resolution(&mut self, width: u32, height: u32) -> Result<()>;
+
+    /// Frame intervals (scale, rate) the open device offers at its current resolution; frame
+    /// rate in fps is `rate / scale`.
+    fn intervals(&self) -> Vec<(u32, u32)>;
+    fn active_interval(&self) -> (u32, u32);
+    /// Tears down and rebuilds the stream at the requested frame interval.
+    fn set_interval(&mut self, scale: u32, rate: u32) -> Result<()>;
+
+    /// Drains any pending hotplug events. Backends without native hotplug support simply return
+    /// an empty list.
+    fn poll_hotplug(&mut self) -> Vec<HotplugEvent> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,378 @@
+//! `libuvc`-backed [`CaptureBackend`], for macOS and Windows where Video4Linux2 isn't available.
+//! Modeled on nokhwa's UVC backend: device enumeration, MJPEG/YUYV streaming and control
+//! get/set are all translated from libuvc's API into the same backend-neutral shapes the `v4l`
+//! backend uses.
+//!
+//! libuvc streams by invoking a callback on its own thread for every frame, so frames are
+//! buffered into a single-slot queue that `next_frame` drains; only the newest frame is kept,
+//! since the UI only ever wants the latest one.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Result};
+use uvc::{Context as UvcContext, FrameFormat};
+
+use crate::backend::{
+    decode, CaptureBackend, ControlCategory, ControlDesc, ControlKind, ControlValue, DeviceInfo,
+    Frame, KnownControl, PixelFormat,
+};
+
+struct QueuedFrame {
+    format: PixelFormat,
+    data: Vec<u8>,
+}
+
+/// A control this backend knows how to read/write, via libuvc's named accessors rather than raw
+/// V4L2-style ids (libuvc has no equivalent of `VIDIOC_QUERYCTRL`).
+struct ManagedControl {
+    desc: ControlDesc,
+    get: fn(&uvc::DeviceHandle) -> Result<i64>,
+    set: fn(&uvc::DeviceHandle, i64) -> Result<()>,
+}
+
+pub struct UvcBackend {
+    // libuvc's streaming callback requires 'static closures, so `handle` is a `'static` reference
+    // derived (unsafely) from `handle_storage`'s stable heap address rather than a true borrow.
+    // Declaration order matters here: fields drop top-to-bottom, so `stream` (which uses
+    // `handle`) is torn down before `handle_storage`/`ctx_storage` free the memory it points
+    // into, and `handle_storage` (which borrows from the context) drops before `ctx_storage`.
+    // This keeps the context and device handle owned by the backend instead of leaked, so they
+    // (and the underlying USB handle) are released when a device is closed or swapped.
+    stream: uvc::ActiveStream<'static, Arc<Mutex<Option<QueuedFrame>>>>,
+    handle: &'static uvc::DeviceHandle<'static>,
+    handle_storage: Box<uvc::DeviceHandle<'static>>,
+    ctx_storage: Box<UvcContext<'static>>,
+    queue: Arc<Mutex<Option<QueuedFrame>>>,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    controls: Vec<ManagedControl>,
+}
+
+impl CaptureBackend for UvcBackend {
+    fn list_devices() -> Result<Vec<DeviceInfo>> {
+        let ctx = uvc_context()?;
+        let devices = ctx.devices().context("Failed to enumerate UVC devices")?;
+
+        Ok(devices
+            .iter()
+            .enumerate()
+            .map(|(index, device)| DeviceInfo {
+                index,
+                name: device_name(device, index),
+            })
+            .collect())
+    }
+
+    fn open(index: usize) -> Result<Self> {
+        let ctx_storage = Box::new(uvc_context()?);
+        // SAFETY: `ctx_storage`'s heap allocation has a stable address for as long as
+        // `UvcBackend` holds onto it (moving the `Box` moves the pointer, not the pointee), and
+        // the struct's field order guarantees `ctx_storage` outlives every reference derived
+        // from it.
+        let ctx: &'static UvcContext<'static> = unsafe { &*(ctx_storage.as_ref() as *const _) };
+
+        let devices = ctx.devices().context("Failed to enumerate UVC devices")?;
+        let device = devices
+            .into_iter()
+            .nth(index)
+            .context("No such UVC device")?;
+
+        let handle_storage = Box::new(device.open().context("Failed to open UVC device")?);
+        // SAFETY: same reasoning as `ctx`, above.
+        let handle: &'static uvc::DeviceHandle<'static> =
+            unsafe { &*(handle_storage.as_ref() as *const _) };
+
+        let stream_format = handle
+            .get_preferred_format(|f| matches!(f.format, FrameFormat::MJPEG | FrameFormat::YUYV))
+            .context("UVC device doesn't support mjpeg or yuyv")?;
+
+        let format = match stream_format.format {
+            FrameFormat::MJPEG => PixelFormat::Mjpg,
+            _ => PixelFormat::Yuyv,
+        };
+        let (width, height) = (stream_format.width, stream_format.height);
+
+        let queue: Arc<Mutex<Option<QueuedFrame>>> = Arc::new(Mutex::new(None));
+
+        let stream = handle
+            .get_stream_handle_with_format(stream_format)
+            .context("Failed to configure UVC stream")?
+            .start_stream(
+                move |frame, queue: &Arc<Mutex<Option<QueuedFrame>>>| {
+                    *queue.lock().unwrap() = Some(QueuedFrame {
+                        format,
+                        data: frame.to_bytes().to_vec(),
+                    });
+                },
+                Arc::clone(&queue),
+            )
+            .context("Failed to start UVC stream")?;
+
+        let controls = known_controls()
+            .into_iter()
+            .filter(|ctrl| (ctrl.get)(handle).is_ok())
+            .collect();
+
+        Ok(Self {
+            stream,
+            handle,
+            handle_storage,
+            ctx_storage,
+            queue,
+            width,
+            height,
+            format,
+            controls,
+        })
+    }
+
+    fn next_frame(&mut self) -> Result<Frame> {
+        let queued = self
+            .queue
+            .lock()
+            .unwrap()
+            .take()
+            .context("No frame available yet")?;
+
+        let rgb = decode(queued.format, &queued.data, self.width, self.height)
+            .context("Failed to decode frame buffer")?;
+
+        Ok(Frame {
+            raw: queued.data,
+            format: queued.format,
+            rgb,
+        })
+    }
+
+    fn controls(&self) -> Vec<ControlDesc> {
+        self.controls.iter().map(|ctrl| ctrl.desc.clone()).collect()
+    }
+
+    fn control_value(&self, id: u32) -> Result<ControlValue> {
+        let ctrl = self
+            .controls
+            .iter()
+            .find(|ctrl| ctrl.desc.id == id)
+            .context("Unknown control id")?;
+
+        Ok(ControlValue::Integer((ctrl.get)(self.handle)?))
+    }
+
+    fn set_control(&mut self, id: u32, value: ControlValue) -> Result<()> {
+        let ControlValue::Integer(value) = value else {
+            bail!("Unsupported control value for UVC control {id}");
+        };
+
+        let ctrl = self
+            .controls
+            .iter()
+            .find(|ctrl| ctrl.desc.id == id)
+            .context("Unknown control id")?;
+
+        (ctrl.set)(self.handle, value)
+    }
+
+    fn resolutions(&self) -> Vec<(u32, u32)> {
+        let Ok(formats) = self.handle.get_format_list() else {
+            return Vec::new();
+        };
+
+        let mut resolutions: Vec<_> = formats
+            .into_iter()
+            .filter(|f| f.format == format_for(self.format))
+            .map(|f| (f.width, f.height))
+            .collect();
+
+        resolutions.sort_unstable();
+        resolutions.dedup();
+
+        resolutions
+    }
+
+    fn active_resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_resolution(&mut self, width: u32, height: u32) -> Result<()> {
+        let fps = self.stream.fps();
+        self.reconfigure(width, height, self.format, fps)
+    }
+
+    fn intervals(&self) -> Vec<(u32, u32)> {
+        let Ok(formats) = self.handle.get_format_list() else {
+            return Vec::new();
+        };
+
+        let mut fps_list: Vec<u32> = formats
+            .into_iter()
+            .filter(|f| f.format == format_for(self.format) && (f.width, f.height) == (self.width, self.height))
+            .map(|f| f.fps)
+            .collect();
+
+        fps_list.sort_unstable();
+        fps_list.dedup();
+
+        fps_list.into_iter().map(|fps| (1, fps)).collect()
+    }
+
+    fn active_interval(&self) -> (u32, u32) {
+        (1, self.stream.fps())
+    }
+
+    fn set_interval(&mut self, scale: u32, rate: u32) -> Result<()> {
+        // `intervals()` only ever hands back `scale == 1` entries (plain fps values), so this
+        // recovers the fps the user actually picked instead of ignoring it.
+        let fps = rate / scale.max(1);
+        self.reconfigure(self.width, self.height, self.format, fps)
+    }
+}
+
+impl UvcBackend {
+    fn reconfigure(&mut self, width: u32, height: u32, format: PixelFormat, fps: u32) -> Result<()> {
+        let stream_format = self
+            .handle
+            .get_format_list()
+            .context("Failed to enumerate UVC formats")?
+            .into_iter()
+            .find(|f| f.format == format_for(format) && (f.width, f.height) == (width, height) && f.fps == fps)
+            .context("UVC device doesn't support that resolution/frame rate")?;
+
+        let stream = self
+            .handle
+            .get_stream_handle_with_format(stream_format)
+            .context("Failed to configure UVC stream")?
+            .start_stream(
+                {
+                    let format = self.format;
+                    move |frame, queue: &Arc<Mutex<Option<QueuedFrame>>>| {
+                        *queue.lock().unwrap() = Some(QueuedFrame {
+                            format,
+                            data: frame.to_bytes().to_vec(),
+                        });
+                    }
+                },
+                Arc::clone(&self.queue),
+            )
+            .context("Failed to start UVC stream")?;
+
+        self.stream = stream;
+        self.width = width;
+        self.height = height;
+        self.format = format;
+        *self.queue.lock().unwrap() = None;
+
+        Ok(())
+    }
+}
+
+fn uvc_context() -> Result<UvcContext<'static>> {
+    UvcContext::new().context("Failed to initialize libuvc")
+}
+
+fn device_name(device: &uvc::Device, index: usize) -> String {
+    device
+        .description()
+        .map(|d| format!("{} {}", d.manufacturer.unwrap_or_default(), d.product.unwrap_or_default()))
+        .unwrap_or_else(|_| format!("UVC device {index}"))
+}
+
+fn format_for(format: PixelFormat) -> FrameFormat {
+    match format {
+        PixelFormat::Mjpg => FrameFormat::MJPEG,
+        PixelFormat::Yuyv => FrameFormat::YUYV,
+    }
+}
+
+const CTRL_BRIGHTNESS: u32 = 1;
+const CTRL_CONTRAST: u32 = 2;
+const CTRL_SATURATION: u32 = 3;
+const CTRL_GAIN: u32 = 4;
+const CTRL_ZOOM: u32 = 5;
+
+/// All controls this backend is willing to surface; `open` keeps only the ones the device
+/// actually supports.
+fn known_controls() -> Vec<ManagedControl> {
+    vec![
+        ManagedControl {
+            desc: ControlDesc {
+                id: CTRL_BRIGHTNESS,
+                name: "Brightness".to_string(),
+                kind: ControlKind::Integer,
+                minimum: i16::MIN as i64,
+                maximum: i16::MAX as i64,
+                step: 1,
+                default: 0,
+                items: None,
+                known: Some(KnownControl::Brightness),
+                category: ControlCategory::Image,
+            },
+            get: |h| Ok(h.brightness()? as i64),
+            set: |h, v| Ok(h.set_brightness(v as i16)?),
+        },
+        ManagedControl {
+            desc: ControlDesc {
+                id: CTRL_CONTRAST,
+                name: "Contrast".to_string(),
+                kind: ControlKind::Integer,
+                minimum: 0,
+                maximum: u16::MAX as i64,
+                step: 1,
+                default: 0,
+                items: None,
+                known: Some(KnownControl::Contrast),
+                category: ControlCategory::Image,
+            },
+            get: |h| Ok(h.contrast()? as i64),
+            set: |h, v| Ok(h.set_contrast(v as u16)?),
+        },
+        ManagedControl {
+            desc: ControlDesc {
+                id: CTRL_SATURATION,
+                name: "Saturation".to_string(),
+                kind: ControlKind::Integer,
+                minimum: 0,
+                maximum: u16::MAX as i64,
+                step: 1,
+                default: 0,
+                items: None,
+                known: Some(KnownControl::Saturation),
+                category: ControlCategory::Image,
+            },
+            get: |h| Ok(h.saturation()? as i64),
+            set: |h, v| Ok(h.set_saturation(v as u16)?),
+        },
+        ManagedControl {
+            desc: ControlDesc {
+                id: CTRL_GAIN,
+                name: "Gain".to_string(),
+                kind: ControlKind::Integer,
+                minimum: 0,
+                maximum: u16::MAX as i64,
+                step: 1,
+                default: 0,
+                items: None,
+                known: None,
+                category: ControlCategory::Other,
+            },
+            get: |h| Ok(h.gain()? as i64),
+            set: |h, v| Ok(h.set_gain(v as u16)?),
+        },
+        ManagedControl {
+            desc: ControlDesc {
+                id: CTRL_ZOOM,
+                name: "Zoom".to_string(),
+                kind: ControlKind::Integer,
+                minimum: 0,
+                maximum: u16::MAX as i64,
+                step: 1,
+                default: 0,
+                items: None,
+                known: Some(KnownControl::Zoom),
+                category: ControlCategory::FocusZoom,
+            },
+            get: |h| Ok(h.zoom_absolute()? as i64),
+            set: |h, v| Ok(h.set_zoom_absolute(v as u16)?),
+        },
+    ]
+}
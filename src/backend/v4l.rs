@@ -0,0 +1,380 @@
+//! Video4Linux2-backed [`CaptureBackend`], for Linux. This is the original (and still primary)
+//! kcam implementation; hotplug detection via udev also lives here, since it's V4L2/Linux
+//! specific.
+
+use std::os::fd::AsRawFd;
+
+use anyhow::{bail, ensure, Context, Result};
+use log::info;
+use udev::EventType;
+use v4l::{
+    buffer,
+    context::{enum_devices, Node},
+    control::{Description, Type as CtrlType, Value as CtrlValue},
+    frameinterval::FrameIntervalEnum,
+    framesize::FrameSizeEnum,
+    io::traits::CaptureStream,
+    prelude::UserptrStream,
+    video::{capture::Parameters, Capture},
+    Control, Device, Format, FourCC, Fraction,
+};
+
+use crate::backend::{
+    decode, known_control, CaptureBackend, ControlCategory, ControlDesc, ControlKind,
+    ControlValue, DeviceInfo, Frame, HotplugEvent, PixelFormat,
+};
+
+const MJPG: [u8; 4] = *b"MJPG";
+const YUYV: [u8; 4] = *b"YUYV";
+
+pub struct V4lBackend {
+    dev: Device,
+    stream: UserptrStream,
+    format: Format,
+    ctrl_descriptors: Vec<Description>,
+    watcher: DeviceWatcher,
+}
+
+impl CaptureBackend for V4lBackend {
+    fn list_devices() -> Result<Vec<DeviceInfo>> {
+        Ok(enum_devices()
+            .into_iter()
+            .filter(check_device)
+            .map(node_info)
+            .collect())
+    }
+
+    fn open(index: usize) -> Result<Self> {
+        let mut dev = Device::new(index).context("Failed to open video device.")?;
+        let (stream, format) = open_stream(&mut dev).context("Failed to open stream.")?;
+        let ctrl_descriptors = get_descriptors(&dev);
+        let watcher = DeviceWatcher::new().context("Failed to start device watcher")?;
+
+        Ok(Self {
+            dev,
+            stream,
+            format,
+            ctrl_descriptors,
+            watcher,
+        })
+    }
+
+    fn next_frame(&mut self) -> Result<Frame> {
+        let (raw, _) = self.stream.next().context("Failed to fetch frame")?;
+        let format = pixel_format(self.format.fourcc)?;
+        let rgb = decode(format, raw, self.format.width, self.format.height)
+            .context("Failed to decode frame buffer")?;
+
+        Ok(Frame {
+            raw: raw.to_vec(),
+            format,
+            rgb,
+        })
+    }
+
+    fn controls(&self) -> Vec<ControlDesc> {
+        self.ctrl_descriptors.iter().map(to_control_desc).collect()
+    }
+
+    fn control_value(&self, id: u32) -> Result<ControlValue> {
+        let value = self.dev.control(id).context("Failed to read control")?.value;
+        to_control_value(value)
+    }
+
+    fn set_control(&mut self, id: u32, value: ControlValue) -> Result<()> {
+        let value = match value {
+            ControlValue::Integer(v) => CtrlValue::Integer(v),
+            ControlValue::Boolean(v) => CtrlValue::Boolean(v),
+        };
+
+        self.dev
+            .set_control(Control { id, value })
+            .context("Failed to set control")
+    }
+
+    fn resolutions(&self) -> Vec<(u32, u32)> {
+        enum_resolutions(&self.dev, self.format.fourcc).unwrap_or_default()
+    }
+
+    fn active_resolution(&self) -> (u32, u32) {
+        (self.format.width, self.format.height)
+    }
+
+    fn set_resolution(&mut self, width: u32, height: u32) -> Result<()> {
+        let mut format = self.dev.format().context("Failed to read device format")?;
+        format.width = width;
+        format.height = height;
+        self.dev
+            .set_format(&format)
+            .context("Failed to set resolution")?;
+
+        (self.stream, self.format) =
+            open_stream(&mut self.dev).context("Failed to rebuild stream")?;
+
+        Ok(())
+    }
+
+    fn intervals(&self) -> Vec<(u32, u32)> {
+        enum_intervals(&self.dev, self.format.fourcc, self.format.width, self.format.height)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|f| (f.numerator, f.denominator))
+            .collect()
+    }
+
+    fn active_interval(&self) -> (u32, u32) {
+        self.dev
+            .params()
+            .map(|params| (params.interval.numerator, params.interval.denominator))
+            .unwrap_or((1, 30))
+    }
+
+    fn set_interval(&mut self, scale: u32, rate: u32) -> Result<()> {
+        let params = Parameters::new(Fraction {
+            numerator: scale,
+            denominator: rate,
+        });
+        self.dev
+            .set_params(&params)
+            .context("Failed to set frame interval")?;
+
+        (self.stream, self.format) =
+            open_stream(&mut self.dev).context("Failed to rebuild stream")?;
+
+        Ok(())
+    }
+
+    fn poll_hotplug(&mut self) -> Vec<HotplugEvent> {
+        self.watcher
+            .poll()
+            .into_iter()
+            .filter_map(|event| match event {
+                DeviceEvent::Added(node) => {
+                    check_device(&node).then(|| HotplugEvent::Added(node_info(node)))
+                }
+                DeviceEvent::Removed(node) => Some(HotplugEvent::Removed(node_info(node))),
+            })
+            .collect()
+    }
+}
+
+fn node_info(node: Node) -> DeviceInfo {
+    DeviceInfo {
+        index: node.index(),
+        name: node.name().unwrap_or_default(),
+    }
+}
+
+fn pixel_format(fourcc: FourCC) -> Result<PixelFormat> {
+    if fourcc == FourCC::new(&MJPG) {
+        Ok(PixelFormat::Mjpg)
+    } else if fourcc == FourCC::new(&YUYV) {
+        Ok(PixelFormat::Yuyv)
+    } else {
+        bail!("Unsupported pixel format: {fourcc}")
+    }
+}
+
+fn to_control_desc(desc: &Description) -> ControlDesc {
+    let kind = match desc.typ {
+        CtrlType::Integer => ControlKind::Integer,
+        CtrlType::Boolean => ControlKind::Boolean,
+        CtrlType::Menu => ControlKind::Menu,
+        _ => ControlKind::Other,
+    };
+
+    let known = known_control(desc.id);
+    let name = known.map_or_else(|| desc.name.clone(), |k| k.label().to_string());
+    let category = known.map_or(ControlCategory::Other, |k| k.category());
+
+    ControlDesc {
+        id: desc.id,
+        name,
+        kind,
+        minimum: desc.minimum,
+        maximum: desc.maximum,
+        step: desc.step,
+        default: desc.default,
+        items: desc
+            .items
+            .as_ref()
+            .map(|items| items.iter().map(|(v, item)| (*v as i64, item.to_string())).collect()),
+        known,
+        category,
+    }
+}
+
+fn to_control_value(value: CtrlValue) -> Result<ControlValue> {
+    match value {
+        CtrlValue::Integer(v) => Ok(ControlValue::Integer(v)),
+        CtrlValue::Boolean(v) => Ok(ControlValue::Boolean(v)),
+        other => bail!("Unsupported control value: {other:?}"),
+    }
+}
+
+/// Negotiates a pixel format and opens a stream, preferring `MJPG` and falling back to `YUYV`
+/// for cameras (and virtual devices) that only expose raw formats.
+fn open_stream(dev: &mut Device) -> Result<(UserptrStream, Format)> {
+    let supported = dev.enum_formats().context("failed to enumerate formats")?;
+
+    let fourcc = supported
+        .iter()
+        .map(|desc| desc.fourcc)
+        .find(|fourcc| *fourcc == FourCC::new(&MJPG))
+        .or_else(|| {
+            supported
+                .iter()
+                .map(|desc| desc.fourcc)
+                .find(|fourcc| *fourcc == FourCC::new(&YUYV))
+        })
+        .context("Video capture device doesn't support jpg or yuyv")?;
+
+    let mut format = dev.format()?;
+    format.fourcc = fourcc;
+
+    let format = dev.set_format(&format).context("failed to set format")?;
+
+    ensure!(
+        format.fourcc == fourcc,
+        "Video capture device rejected the negotiated pixel format"
+    );
+
+    let stream =
+        UserptrStream::new(dev, buffer::Type::VideoCapture).context("Failed to begin stream")?;
+
+    Ok((stream, format))
+}
+
+fn check_device(node: &Node) -> bool {
+    let check = |node: &Node| -> Result<()> {
+        let mut dev = Device::new(node.index()).context("Failed to open video device.")?;
+        open_stream(&mut dev).context("Failed to open stream.")?;
+        Ok(())
+    };
+
+    let res = check(node);
+
+    match &res {
+        Ok(()) => info!(
+            "Device check passed for {:?} at {:?}",
+            node.name(),
+            node.path(),
+        ),
+        Err(e) => info!(
+            "Device check failed for {:?} at {:?}: {:?}",
+            node.name(),
+            node.path(),
+            e
+        ),
+    }
+
+    res.is_ok()
+}
+
+/// Query available controls and sort them by type. Sorting improves the layout of control widgets.
+fn get_descriptors(dev: &Device) -> Vec<Description> {
+    let mut ctrl_descriptors = dev.query_controls().unwrap_or_default();
+    ctrl_descriptors.sort_by(|a, b| (a.typ as u32).cmp(&(b.typ as u32)));
+
+    ctrl_descriptors
+}
+
+/// Enumerates the resolutions a device offers for `fourcc`. Stepwise ranges are offered as their
+/// min and max, since stepwise V4L2 sizes are usually sparse in practice.
+fn enum_resolutions(dev: &Device, fourcc: FourCC) -> Result<Vec<(u32, u32)>> {
+    let sizes = dev
+        .enum_framesizes(fourcc)
+        .context("failed to enumerate frame sizes")?;
+
+    let mut resolutions: Vec<_> = sizes
+        .into_iter()
+        .flat_map(|frame_size| match frame_size.size {
+            FrameSizeEnum::Discrete(d) => vec![(d.width, d.height)],
+            FrameSizeEnum::Stepwise(s) => {
+                vec![(s.min_width, s.min_height), (s.max_width, s.max_height)]
+            }
+        })
+        .collect();
+
+    resolutions.sort_unstable();
+    resolutions.dedup();
+
+    Ok(resolutions)
+}
+
+/// Enumerates the frame intervals a device offers at `width`x`height` for `fourcc`. Stepwise
+/// ranges are offered as their min and max, mirroring `enum_resolutions`.
+fn enum_intervals(dev: &Device, fourcc: FourCC, width: u32, height: u32) -> Result<Vec<Fraction>> {
+    let intervals = dev
+        .enum_frameintervals(fourcc, width, height)
+        .context("failed to enumerate frame intervals")?;
+
+    let mut intervals: Vec<_> = intervals
+        .into_iter()
+        .flat_map(|fi| match fi.interval {
+            FrameIntervalEnum::Discrete(f) => vec![f],
+            FrameIntervalEnum::Stepwise(s) => vec![s.min, s.max],
+        })
+        .collect();
+
+    intervals.sort_unstable_by_key(|f| (f.numerator, f.denominator));
+    intervals.dedup_by_key(|f| (f.numerator, f.denominator));
+
+    Ok(intervals)
+}
+
+/// A hotplug event for a `/dev/videoN` node, as reported by udev.
+enum DeviceEvent {
+    Added(Node),
+    Removed(Node),
+}
+
+/// Watches udev for `video4linux` add/remove events so the device list can stay live without
+/// re-enumerating every frame.
+struct DeviceWatcher {
+    monitor: udev::MonitorSocket,
+}
+
+impl DeviceWatcher {
+    fn new() -> Result<Self> {
+        let monitor = udev::MonitorBuilder::new()
+            .context("Failed to create udev monitor")?
+            .match_subsystem("video4linux")
+            .context("Failed to filter udev monitor")?
+            .listen()
+            .context("Failed to listen on udev monitor")?;
+
+        // We poll this socket once per frame rather than blocking on it, so make reads
+        // non-blocking: an empty read just means "nothing changed since last frame".
+        let fd = monitor.as_raw_fd();
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        ensure!(flags >= 0, "Failed to read udev socket flags");
+        ensure!(
+            unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } >= 0,
+            "Failed to set udev socket non-blocking"
+        );
+
+        Ok(Self { monitor })
+    }
+
+    /// Drain any pending hotplug events without blocking.
+    fn poll(&mut self) -> Vec<DeviceEvent> {
+        self.monitor
+            .by_ref()
+            .filter_map(|event| {
+                let node = Node::new(device_index(&event.devnode()?.to_path_buf())?);
+
+                match event.event_type() {
+                    EventType::Add => Some(DeviceEvent::Added(node)),
+                    EventType::Remove => Some(DeviceEvent::Removed(node)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parses the `N` out of a `/dev/videoN` device node path.
+fn device_index(devnode: &std::path::Path) -> Option<usize> {
+    devnode.to_str()?.strip_prefix("/dev/video")?.parse().ok()
+}
@@ -1,21 +1,31 @@
 use std::process::Termination;
 
-use anyhow::{Context, Result, ensure};
+use anyhow::{ensure, Context, Result};
 use eframe::{
+    egui::{self, CentralPanel, CollapsingHeader, ComboBox, Image, SidePanel, Slider, TextureOptions},
     App, NativeOptions,
-    egui::{self, CentralPanel, ComboBox, Image, SidePanel, Slider, TextureOptions},
 };
 use log::{debug, error};
-use v4l::{
-    Control,
-    context::{Node, enum_devices},
-    control::{Description, Type, Value},
-    io::traits::CaptureStream,
-    prelude::*,
+
+mod avi;
+use avi::AviWriter;
+
+mod backend;
+use backend::{
+    CaptureBackend, ControlCategory, ControlKind, ControlValue, DeviceInfo, Frame, HotplugEvent,
 };
 
 mod util;
-use util::{Frame, capture, check_device, decode, get_descriptors, get_stream};
+use util::{capture, frame_jpg, video_path};
+
+#[cfg(feature = "v4l")]
+type Backend = backend::v4l::V4lBackend;
+
+#[cfg(feature = "uvc")]
+type Backend = backend::uvc::UvcBackend;
+
+#[cfg(not(any(feature = "v4l", feature = "uvc")))]
+compile_error!("kcam needs exactly one capture backend enabled: \"v4l\" or \"uvc\"");
 
 fn main() -> impl Termination {
     env_logger::init();
@@ -32,7 +42,7 @@ fn main() -> impl Termination {
 }
 struct KCam {
     /// A list of all available video devices on the system
-    available_devices: Vec<Node>,
+    available_devices: Vec<DeviceInfo>,
 
     /// The index of the currently selected device in the list of `available_devices`
     selected_device: usize,
@@ -40,22 +50,31 @@ struct KCam {
     /// Has the device selection changed?
     device_changed: bool,
 
-    /// Handle to video capture device
-    dev: Device,
+    /// Handle to the active platform capture backend (V4L2 on Linux, UVC elsewhere)
+    backend: Box<dyn CaptureBackend>,
 
-    /// V4l buffer stream
-    stream: UserptrStream,
+    /// Resolutions offered by the current device at the active pixel format
+    resolutions: Vec<(u32, u32)>,
+
+    /// The index of the active entry in `resolutions`
+    selected_resolution: usize,
+
+    /// Frame intervals offered by the current device at the active resolution
+    intervals: Vec<(u32, u32)>,
+
+    /// The index of the active entry in `intervals`
+    selected_interval: usize,
 
     /// A status message to display
     message: String,
 
-    /// Descriptions of available controls
-    ctrl_descriptors: Vec<Description>,
+    /// Open MJPEG AVI writer while a clip is being recorded, `None` otherwise
+    recording: Option<AviWriter>,
 }
 
 impl KCam {
     fn new() -> Result<Self> {
-        let available_devices: Vec<_> = enum_devices().into_iter().filter(check_device).collect();
+        let available_devices = Backend::list_devices().context("Failed to list devices")?;
         let selected_device = 0; // first device in the list
 
         ensure!(
@@ -63,55 +82,175 @@ impl KCam {
             "No capable video devices found. Run with RUST_LOG=info for details."
         );
 
-        let mut dev = Device::new(available_devices[selected_device].index())
-            .context("Failed to open video device.")?;
-        let stream = get_stream(&mut dev).context("Failed to open stream.")?;
+        let backend =
+            Backend::open(available_devices[selected_device].index).context("Failed to open video device.")?;
+        let resolutions = backend.resolutions();
+        let selected_resolution = resolutions
+            .iter()
+            .position(|&res| res == backend.active_resolution())
+            .unwrap_or(0);
+        let intervals = backend.intervals();
+        let selected_interval = intervals
+            .iter()
+            .position(|&iv| iv == backend.active_interval())
+            .unwrap_or(0);
 
         Ok(Self {
             device_changed: false,
-            stream,
-            ctrl_descriptors: get_descriptors(&dev),
-            dev,
+            backend: Box::new(backend),
+            resolutions,
+            selected_resolution,
+            intervals,
+            selected_interval,
             message: String::default(),
             selected_device,
             available_devices,
+            recording: None,
         })
     }
 
     fn open_device(&mut self, index: usize) -> Result<()> {
-        let mut dev = Device::new(index).context("Failed to open video device.")?;
-        self.stream = get_stream(&mut dev).context("Failed to open stream.")?;
-        self.ctrl_descriptors = get_descriptors(&dev);
-        self.dev = dev;
+        self.stop_recording();
+
+        let backend = Backend::open(index).context("Failed to open video device.")?;
+        self.resolutions = backend.resolutions();
+        self.selected_resolution = self
+            .resolutions
+            .iter()
+            .position(|&res| res == backend.active_resolution())
+            .unwrap_or(0);
+        self.intervals = backend.intervals();
+        self.selected_interval = self
+            .intervals
+            .iter()
+            .position(|&iv| iv == backend.active_interval())
+            .unwrap_or(0);
+        self.backend = Box::new(backend);
+
+        Ok(())
+    }
+
+    /// Changes the active resolution and rebuilds the stream, since capture devices generally
+    /// can't change format mid-capture.
+    fn set_resolution(&mut self, index: usize) -> Result<()> {
+        self.stop_recording();
+
+        let (width, height) = self.resolutions[index];
+        self.backend
+            .set_resolution(width, height)
+            .context("Failed to change resolution")?;
+
+        self.selected_resolution = index;
+        self.intervals = self.backend.intervals();
+        self.selected_interval = self
+            .intervals
+            .iter()
+            .position(|&iv| iv == self.backend.active_interval())
+            .unwrap_or(0);
+
+        Ok(())
+    }
+
+    /// Changes the active frame interval and rebuilds the stream, since capture devices generally
+    /// can't change params mid-capture.
+    fn set_interval(&mut self, index: usize) -> Result<()> {
+        self.stop_recording();
+
+        let (scale, rate) = self.intervals[index];
+        self.backend
+            .set_interval(scale, rate)
+            .context("Failed to change frame rate")?;
+
+        self.selected_interval = index;
 
         Ok(())
     }
+
+    /// Starts or stops recording the current stream to a Motion-JPEG AVI, returning a status
+    /// message for display.
+    fn toggle_recording(&mut self) -> Result<String> {
+        if let Some(writer) = self.recording.take() {
+            let frames = writer.frame_count();
+            writer.finish().context("Failed to finalize recording")?;
+
+            return Ok(format!("Saved recording ({frames} frames)"));
+        }
+
+        let path = video_path().context("Failed to choose recording path")?;
+        let (width, height) = self.backend.active_resolution();
+        let (scale, rate) = self.intervals.get(self.selected_interval).copied().unwrap_or((1, 30));
+
+        let writer =
+            AviWriter::create(&path, width, height, scale, rate).context("Failed to start recording")?;
+
+        self.recording = Some(writer);
+
+        Ok(format!("Recording to {}", path.display()))
+    }
+
+    /// Finalizes any in-progress recording without reporting a status message, for use when the
+    /// stream is about to be torn down (e.g. on a device, resolution or frame-rate change).
+    fn stop_recording(&mut self) {
+        if let Some(writer) = self.recording.take() {
+            if let Err(e) = writer.finish() {
+                debug!("Failed to finalize interrupted recording: {e:?}");
+            }
+        }
+    }
 }
 
 impl App for KCam {
-    fn update<'a>(&'a mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
         catppuccin_egui::set_theme(&ctx, catppuccin_egui::FRAPPE); // this looks nice.
 
-        if self.device_changed {
-            let device_index = self.available_devices[self.selected_device].index();
+        for event in self.backend.poll_hotplug() {
+            match event {
+                HotplugEvent::Added(info) => self.available_devices.push(info),
+                HotplugEvent::Removed(info) => {
+                    let Some(pos) = self
+                        .available_devices
+                        .iter()
+                        .position(|dev| dev.index == info.index)
+                    else {
+                        continue;
+                    };
+
+                    self.available_devices.remove(pos);
+
+                    if pos == self.selected_device {
+                        self.selected_device = 0;
+                        self.device_changed = true;
+                    } else if pos < self.selected_device {
+                        // Everything past `pos` shifted down by one.
+                        self.selected_device -= 1;
+                    }
+                }
+            }
+        }
 
-            if let Err(e) = self.open_device(device_index) {
-                // Generally unlikely to fail since we check all devices on startup.
-                // If an external webcam is unplugged, we'll probably end up here.
-                error!("{e:?}");
+        if self.device_changed {
+            match self.available_devices.get(self.selected_device) {
+                Some(dev) => {
+                    if let Err(e) = self.open_device(dev.index) {
+                        // Generally unlikely to fail since devices are checked on startup and on
+                        // hotplug, and unplugged devices are dropped from the list above.
+                        error!("{e:?}");
+                    }
+                }
+                None => self.message = "No camera connected".to_string(),
             }
 
             self.device_changed = false;
         }
 
-        let next_frame = |stream: &'a mut UserptrStream| -> Result<Frame> {
-            let (jpg, _) = stream.next().context("Failed to fetch frame")?;
-            let rgb = decode(jpg).context("Failed to decode jpg buffer")?;
-
-            Ok(Frame { jpg, rgb })
-        };
+        let frame = self.backend.next_frame().context("Failed to fetch frame");
 
-        let frame = next_frame(&mut self.stream);
+        if let (Ok(frame), Some(writer)) = (&frame, &mut self.recording) {
+            match frame_jpg(frame).and_then(|jpg| writer.write_frame(&jpg)) {
+                Ok(()) => {}
+                Err(e) => error!("Failed to write recording frame: {e:?}"),
+            }
+        }
 
         SidePanel::left("Options").show(ctx, |sidebar| {
             sidebar.spacing_mut().item_spacing.y = 10.0;
@@ -126,40 +265,94 @@ impl App for KCam {
                 |i| {
                     let dev = &self.available_devices[i];
 
-                    format!("{}: {}", dev.index(), dev.name().unwrap_or_default())
+                    format!("{}: {}", dev.index, dev.name)
                 },
             );
 
             // `changed()` would be more idiomatic but gives false positives if the same device is selected.
             self.device_changed = self.selected_device != current_device;
 
+            if !self.resolutions.is_empty() {
+                let current_resolution = self.selected_resolution;
+                ComboBox::new("resolution selector", "Resolution").show_index(
+                    sidebar,
+                    &mut self.selected_resolution,
+                    self.resolutions.len(),
+                    |i| {
+                        let (w, h) = self.resolutions[i];
+                        format!("{w}x{h}")
+                    },
+                );
+
+                if self.selected_resolution != current_resolution {
+                    if let Err(e) = self.set_resolution(self.selected_resolution) {
+                        error!("{e:?}");
+                        self.message = format!("Failed to change resolution: {e:?}");
+                    }
+                }
+            }
+
+            if !self.intervals.is_empty() {
+                let current_interval = self.selected_interval;
+                ComboBox::new("frame rate selector", "Frame rate").show_index(
+                    sidebar,
+                    &mut self.selected_interval,
+                    self.intervals.len(),
+                    |i| {
+                        let (scale, rate) = self.intervals[i];
+                        format!("{:.0} fps", rate as f64 / scale as f64)
+                    },
+                );
+
+                if self.selected_interval != current_interval {
+                    if let Err(e) = self.set_interval(self.selected_interval) {
+                        error!("{e:?}");
+                        self.message = format!("Failed to change frame rate: {e:?}");
+                    }
+                }
+            }
+
             sidebar.separator();
 
             if let Ok(frame) = &frame {
                 if sidebar.button("Take Photo").clicked() {
-                    self.message = match capture(frame.jpg) {
+                    self.message = match capture(frame) {
                         Ok(path) => format!("Saved capture: {}", path.display()),
                         Err(e) => format!("Failed to take photo: {e:?}"),
                     };
                 }
             }
 
+            let record_label = if self.recording.is_some() {
+                "Stop"
+            } else {
+                "Record"
+            };
+            if sidebar.button(record_label).clicked() {
+                self.message = match self.toggle_recording() {
+                    Ok(msg) => msg,
+                    Err(e) => format!("Recording failed: {e:?}"),
+                };
+            }
+
             if sidebar.button("Reset").clicked() {
                 // Set each control to the default value provided by its descriptor.
-                for desc in &self.ctrl_descriptors {
-                    let value = match desc.typ {
-                        Type::Integer | Type::Menu => Value::Integer(desc.default),
-                        Type::Boolean => Value::Boolean(desc.default != 0),
-                        _ => continue,
+                for desc in self.backend.controls() {
+                    let value = match desc.kind {
+                        ControlKind::Integer | ControlKind::Menu => ControlValue::Integer(desc.default),
+                        ControlKind::Boolean => ControlValue::Boolean(desc.default != 0),
+                        ControlKind::Other => continue,
                     };
 
-                    if let Err(e) = self.dev.set_control(Control { value, id: desc.id }) {
+                    if let Err(e) = self.backend.set_control(desc.id, value) {
                         debug!("Unable to set {}: {}", desc.name, e);
                     }
                 }
             }
 
-            // Procedurally add widgets for each available control.
+            // Procedurally add widgets for each available control, grouped into collapsible
+            // sections by category so the layout is predictable across different cameras rather
+            // than dependent on raw driver enum ordering.
             //
             // +-----------------------------+
             // | Control Type -> Widget Type |
@@ -168,86 +361,104 @@ impl App for KCam {
             // | Boolean      -> Checkbox    |
             // | Menu         -> Dropdown    |
             // +-----------------------------+
-            for desc in &mut self.ctrl_descriptors {
-                let current_val = match self.dev.control(desc.id) {
-                    Ok(ctrl) => ctrl.value,
-                    Err(e) => {
-                        debug!("Failed to get value for {:?}: {:?}", desc.name, e);
-                        continue;
-                    }
-                };
-
-                match desc.typ {
-                    Type::Integer => {
-                        let mut value = match current_val {
-                            Value::Integer(v) => v,
-                            _ => unreachable!(),
-                        };
-
-                        let slider = Slider::new(&mut value, desc.minimum..=desc.maximum)
-                            .step_by(desc.step as f64)
-                            .text(&desc.name);
-
-                        if sidebar.add(slider).changed() {
-                            let ctrl = Control {
-                                value: Value::Integer(value),
-                                id: desc.id,
-                            };
+            const SECTIONS: [(ControlCategory, &str); 4] = [
+                (ControlCategory::Image, "Image"),
+                (ControlCategory::Exposure, "Exposure"),
+                (ControlCategory::FocusZoom, "Focus/Zoom"),
+                (ControlCategory::Other, "Other"),
+            ];
+
+            let controls = self.backend.controls();
+
+            for (category, label) in SECTIONS {
+                let section: Vec<_> = controls.iter().filter(|desc| desc.category == category).collect();
+                if section.is_empty() {
+                    continue;
+                }
 
-                            if let Err(e) = self.dev.set_control(ctrl) {
-                                debug!("Unable to set {}: {}", desc.name, e);
+                CollapsingHeader::new(label).default_open(true).show(sidebar, |ui| {
+                    for desc in section {
+                        let current_val = match self.backend.control_value(desc.id) {
+                            Ok(value) => value,
+                            Err(e) => {
+                                debug!("Failed to get value for {:?}: {:?}", desc.name, e);
+                                continue;
                             }
-                        }
-                    }
-                    Type::Boolean => {
-                        let mut value = match current_val {
-                            Value::Boolean(v) => v,
-                            _ => unreachable!(),
                         };
 
-                        if sidebar.checkbox(&mut value, &desc.name).changed() {
-                            let ctrl = Control {
-                                value: Value::Boolean(value),
-                                id: desc.id,
-                            };
-
-                            if let Err(e) = self.dev.set_control(ctrl) {
-                                debug!("Unable to set {}: {}", desc.name, e);
+                        match desc.kind {
+                            ControlKind::Integer => {
+                                let mut value = match current_val {
+                                    ControlValue::Integer(v) => v,
+                                    _ => unreachable!(),
+                                };
+
+                                let slider = Slider::new(&mut value, desc.minimum..=desc.maximum)
+                                    .step_by(desc.step as f64)
+                                    .text(&desc.name);
+
+                                if ui.add(slider).changed() {
+                                    if let Err(e) =
+                                        self.backend.set_control(desc.id, ControlValue::Integer(value))
+                                    {
+                                        debug!("Unable to set {}: {}", desc.name, e);
+                                    }
+                                }
                             }
-                        }
-                    }
-                    Type::Menu => {
-                        let menu_items: Vec<_> = match desc.items.as_ref() {
-                            Some(items) => items.iter(),
-                            None => continue, // unlikely edge case: menu with no items
-                        }
-                        .map(|(v, item)| (Value::Integer(*v as i64), item.to_string()))
-                        .collect();
-
-                        let selected = menu_items
-                            .iter()
-                            .find_map(|(v, label)| (*v == current_val).then_some(label.to_owned()))
-                            .unwrap();
-
-                        let mut new_val = None;
-                        ComboBox::from_label(&desc.name)
-                            .selected_text(&selected)
-                            .show_ui(sidebar, |ui| {
-                                new_val = menu_items.into_iter().find_map(|(v, label)| {
-                                    ui.selectable_label(selected == *label, label)
-                                        .clicked()
-                                        .then_some(v)
-                                });
-                            });
-
-                        if let Some(value) = new_val {
-                            if let Err(e) = self.dev.set_control(Control { value, id: desc.id }) {
-                                debug!("Unable to set {}: {}", desc.name, e);
+                            ControlKind::Boolean => {
+                                let mut value = match current_val {
+                                    ControlValue::Boolean(v) => v,
+                                    _ => unreachable!(),
+                                };
+
+                                if ui.checkbox(&mut value, &desc.name).changed() {
+                                    if let Err(e) =
+                                        self.backend.set_control(desc.id, ControlValue::Boolean(value))
+                                    {
+                                        debug!("Unable to set {}: {}", desc.name, e);
+                                    }
+                                }
+                            }
+                            ControlKind::Menu => {
+                                let menu_items: Vec<_> = match desc.items.as_ref() {
+                                    Some(items) => items.clone(),
+                                    None => continue, // unlikely edge case: menu with no items
+                                };
+
+                                let ControlValue::Integer(current) = current_val else {
+                                    continue;
+                                };
+
+                                let selected = menu_items
+                                    .iter()
+                                    .find_map(|(v, label)| (*v == current).then(|| label.clone()))
+                                    .unwrap_or_default();
+
+                                let mut new_val = None;
+                                ComboBox::from_label(&desc.name)
+                                    .selected_text(&selected)
+                                    .show_ui(ui, |ui| {
+                                        new_val = menu_items.into_iter().find_map(|(v, label)| {
+                                            ui.selectable_label(selected == label, &label)
+                                                .clicked()
+                                                .then_some(v)
+                                        });
+                                    });
+
+                                if let Some(value) = new_val {
+                                    if let Err(e) =
+                                        self.backend.set_control(desc.id, ControlValue::Integer(value))
+                                    {
+                                        debug!("Unable to set {}: {}", desc.name, e);
+                                    }
+                                }
+                            }
+                            ControlKind::Other => {
+                                debug!("Unhandled available ctrl: {:?} of kind {:?}", desc.name, desc.kind)
                             }
                         }
                     }
-                    t => debug!("Unhandled available ctrl: {:?} of type {:?}", desc.name, t),
-                }
+                });
             }
 
             if let Err(e) = &frame {
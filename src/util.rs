@@ -1,22 +1,14 @@
 use std::{fs, path::PathBuf};
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{Context, Result};
 use chrono::Local;
-use eframe::epaint::ColorImage;
-use image::{codecs::jpeg::JpegDecoder, DynamicImage};
-use log::{debug, info};
-use v4l::{
-    buffer, context::Node, control::Description, prelude::UserptrStream, video::Capture, Device,
-    FourCC,
-};
-
-pub struct Frame<'a> {
-    pub jpg: &'a [u8],
-    pub rgb: ColorImage,
-}
+use eframe::epaint::{Color32, ColorImage};
+use image::{codecs::jpeg::JpegEncoder, ColorType};
+
+use crate::backend::{Frame, PixelFormat};
 
-/// Saves jpg buffer to ~/Pictures/kcam if possible, or the current directory otherwise.
-pub fn capture(img: &[u8]) -> Result<PathBuf> {
+/// Saves a captured frame to ~/Pictures/kcam if possible, or the current directory otherwise.
+pub fn capture(frame: &Frame) -> Result<PathBuf> {
     let save_img = |parent_dir: PathBuf| -> Result<PathBuf> {
         let save_dir = parent_dir.join("kcam");
 
@@ -24,7 +16,7 @@ pub fn capture(img: &[u8]) -> Result<PathBuf> {
         let ts = Local::now().format("%Y-%m-%d_%H-%M-%S-%3f");
         let path = save_dir.join(format!("{ts}.jpg"));
 
-        fs::write(&path, img).context("unable to write image")?;
+        fs::write(&path, frame_jpg(frame)?).context("unable to write image")?;
         Ok(path)
     };
 
@@ -35,63 +27,41 @@ pub fn capture(img: &[u8]) -> Result<PathBuf> {
     save_img(save_dir).or_else(|_| save_img(PathBuf::default()))
 }
 
-pub fn decode(jpg_img: &[u8]) -> Result<ColorImage> {
-    let de = JpegDecoder::new(jpg_img)?;
-    let img = DynamicImage::from_decoder(de)?.to_rgba8();
-    let size = [img.width() as _, img.height() as _];
-    let egui_img = ColorImage::from_rgba_unmultiplied(size, img.as_flat_samples().as_slice());
-
-    Ok(egui_img)
-}
-
-pub fn get_stream(dev: &mut Device) -> Result<UserptrStream> {
-    let mut format = dev.format()?;
-    format.fourcc = FourCC::new(b"MJPG");
+/// Resolves a fresh `~/Videos/kcam/<timestamp>.avi` path, creating the directory if needed, or
+/// falling back to the current directory.
+pub fn video_path() -> Result<PathBuf> {
+    let make_path = |parent_dir: PathBuf| -> Result<PathBuf> {
+        let save_dir = parent_dir.join("kcam");
 
-    let format = dev.set_format(&format).context("failed to set format")?;
-    let params = dev.params().context("failed to get device params")?;
+        fs::create_dir_all(&save_dir)?;
+        let ts = Local::now().format("%Y-%m-%d_%H-%M-%S-%3f");
 
-    ensure!(
-        format.fourcc == FourCC::new(b"MJPG"),
-        "Video capture device doesn't support jpg"
-    );
+        Ok(save_dir.join(format!("{ts}.avi")))
+    };
 
-    debug!("Active format:\n{}", format);
-    debug!("Active parameters:\n{}", params);
+    let save_dir = dirs::video_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join("Videos")))
+        .unwrap_or_default();
 
-    UserptrStream::new(dev, buffer::Type::VideoCapture).context("Failed to begin stream")
+    make_path(save_dir).or_else(|_| make_path(PathBuf::default()))
 }
 
-pub fn check_device(node: &Node) -> bool {
-    let check = |node: &Node| -> Result<()> {
-        let mut dev = Device::new(node.index()).context("Failed to open video device.")?;
-        get_stream(&mut dev).context("Failed to open stream.")?;
-        Ok(())
-    };
-
-    let res = check(node);
-
-    match &res {
-        Ok(()) => info!(
-            "Device check passed for {:?} at {:?}",
-            node.name(),
-            node.path(),
-        ),
-        Err(e) => info!(
-            "Device check failed for {:?} at {:?}: {:?}",
-            node.name(),
-            node.path(),
-            e
-        ),
+/// Returns `frame`'s pixel data as a JPEG buffer: verbatim for `MJPG` frames, or freshly encoded
+/// from the already-decoded RGB image for other formats.
+pub fn frame_jpg(frame: &Frame) -> Result<Vec<u8>> {
+    if frame.format == PixelFormat::Mjpg {
+        Ok(frame.raw.clone())
+    } else {
+        encode_jpeg(&frame.rgb)
     }
-
-    res.is_ok()
 }
 
-/// Query available controls and sort them by type. Sorting improves the layout of control widgets.
-pub fn get_descriptors(dev: &Device) -> Vec<Description> {
-    let mut ctrl_descriptors = dev.query_controls().unwrap_or_default();
-    ctrl_descriptors.sort_by(|a, b| (a.typ as u32).cmp(&(b.typ as u32)));
+fn encode_jpeg(img: &ColorImage) -> Result<Vec<u8>> {
+    let [width, height] = img.size;
+    let rgba: Vec<u8> = img.pixels.iter().flat_map(Color32::to_array).collect();
+
+    let mut jpg = Vec::new();
+    JpegEncoder::new(&mut jpg).encode(&rgba, width as u32, height as u32, ColorType::Rgba8)?;
 
-    ctrl_descriptors
+    Ok(jpg)
 }